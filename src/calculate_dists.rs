@@ -0,0 +1,495 @@
+use crate::distance::DistanceMetric;
+use crate::geometric_median::order_by_distance_from_median;
+use crate::kdtree::KdTree;
+use crate::vector_fns::get_ordering_index;
+use nalgebra::DMatrix;
+
+/// Selects the algorithm used to match entries of `values1` to entries of `values2` in
+/// [`calculate_dists`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchingMode {
+    /// Legacy greedy nearest-neighbour matching. Values are visited in order of ascending
+    /// distance from the geometric median of `values1`, and each is matched to the nearest
+    /// not-yet-used value of `values2`. This is still sensitive to the traversal order, and is
+    /// not globally optimal.
+    Greedy,
+    /// Globally optimal minimum-cost one-to-one matching. For the single-variable case this is
+    /// found by sorting both vectors and pairing equal ranks; the general case is solved with a
+    /// Hungarian (Kuhn-Munkres) assignment over the full cost matrix.
+    Optimal,
+}
+
+/// Calculates a vector of sequential difference between two arrays of f64 values.
+///
+/// The distances are calculated in the full multi-dimensional space spanned by all rows of
+/// `values1` and `values2` (`varname` plus any `varextra`), using `metric` to compare points, so
+/// that each column in the first array (`values1`) is matched to the column in `values2` at the
+/// minimal distance. Each column of `values2` is matched to one unique column of `values1`. The
+/// returned differences, however, are always differences in the first row only (`varname`),
+/// since that is the variable being mutated.
+///
+/// With `mode` set to [`MatchingMode::Greedy`], unique matching is done with a hash set, visiting
+/// `values1` points in order of ascending distance from their geometric median (see
+/// [`crate::geometric_median`]), a robust, outlier-resistant reference point that doesn't
+/// privilege any single variable. With `mode` set to [`MatchingMode::Optimal`], the matching instead minimises the
+/// total cost over all pairs, which is both deterministic and independent of any starting point.
+/// For single-row input, this optimum is found directly by sorting (see
+/// [`optimal_match_1d`]), which minimises total absolute difference and does not use `metric`;
+/// for multi-row input, it is found with [`hungarian_assignment`] over the
+/// full `metric` cost matrix.
+///
+/// # Arguments
+///
+/// * `values1` - A `DMatrix` object which provides the reference values against which to sort
+/// `values2`.
+/// * `values2` - A `DMatrix` object which is to be sorted against `values1`.
+/// * `absolute` - A boolean indicating whether to calculate absolute differences.
+/// * `mode` - The [`MatchingMode`] used to pair entries of `values1` with entries of `values2`.
+/// * `metric` - The [`DistanceMetric`] used to compare points across all rows.
+///
+/// # Panics
+///
+/// This function will panic if `values1` is empty or if `values1` and `values2` have different
+/// dimensions.
+///
+/// # Returns
+///
+/// A vector of `f64` differences, one for each consecutive element in `values1`, between that
+/// element and the element of `values2` it is matched to.  If `absolute` is true, the differences are absolute values. Otherwise,
+/// the differences are differences relative to `values1`.
+///
+/// # Example
+///
+/// ```
+/// use uamutations::calculate_dists::{calculate_dists, MatchingMode};
+/// use uamutations::distance::Euclidean;
+/// let values1 = nalgebra::DMatrix::from_row_slice(1, 4, &[1.0, 2.0, 4.0, 5.0]);
+/// let values2 = nalgebra::DMatrix::from_row_slice(1, 4, &[7.0, 9.0, 3.0, 2.0]);
+/// let result = calculate_dists(&values1, &values2, true, MatchingMode::Optimal, &Euclidean);
+/// // Each values1 entry is paired with the values2 entry that minimises total matching cost, and
+/// // result holds (v2 - v1) for each pair. So v1[0] = 1 is paired with v2 = 2, giving 2 - 1 = 1;
+/// // v1[3] = 5 is paired with v2 = 9, giving 9 - 5 = 4.
+/// assert_eq!(result, vec![1.0, 1.0, 3.0, 4.0]);
+/// let result = calculate_dists(&values1, &values2, false, MatchingMode::Optimal, &Euclidean);
+/// assert_eq!(result, vec![1.0, 0.5, 0.75, 0.8]);
+/// ```
+pub fn calculate_dists(
+    values1: &DMatrix<f64>,
+    values2: &DMatrix<f64>,
+    absolute: bool,
+    mode: MatchingMode,
+    metric: &dyn DistanceMetric,
+) -> Vec<f64> {
+    assert!(values1.ncols() > 0, "values1 must not be empty");
+    assert_eq!(
+        values1.shape(),
+        values2.shape(),
+        "values1 and values2 must have the same dimensions."
+    );
+
+    let values1_ref_var: Vec<f64> = values1.row(0).iter().cloned().collect();
+    let values2_ref_var: Vec<f64> = values2.row(0).iter().cloned().collect();
+
+    let points1: Vec<Vec<f64>> = (0..values1.ncols())
+        .map(|c| values1.column(c).iter().cloned().collect())
+        .collect();
+    let points2: Vec<Vec<f64>> = (0..values2.ncols())
+        .map(|c| values2.column(c).iter().cloned().collect())
+        .collect();
+
+    let matched_indices = match mode {
+        MatchingMode::Greedy => greedy_match(&points1, &points2, metric),
+        MatchingMode::Optimal => {
+            if values1.nrows() == 1 {
+                optimal_match_1d(&values1_ref_var, &values2_ref_var)
+            } else {
+                optimal_match_nd(&points1, &points2, metric)
+            }
+        }
+    };
+
+    let mut nearest_dists = vec![0.0; matched_indices.len()];
+    for (i, &j) in matched_indices.iter().enumerate() {
+        let v1 = values1_ref_var[i];
+        nearest_dists[i] = if absolute {
+            values2_ref_var[j] - v1
+        } else {
+            (values2_ref_var[j] - v1) / v1
+        };
+    }
+
+    nearest_dists
+}
+
+/// Below this number of `values2` entries, the overhead of building a [`KdTree`] outweighs its
+/// benefit over a dense scan.
+const KDTREE_MIN_SIZE: usize = 256;
+
+/// Match entries of `values1` to entries of `values2` by greedily taking, in order of ascending
+/// distance from the geometric median of `points1`, the not-yet-used entry of `values2` nearest
+/// in `metric`.
+///
+/// For `points2` of at least [`KDTREE_MIN_SIZE`] entries, nearest-unused lookups are done with a
+/// [`KdTree`] rather than a dense scan, but only if `metric` supports exact pruning (see
+/// [`DistanceMetric::supports_kdtree_pruning`]); smaller inputs, and metrics the tree can't prune
+/// exactly for, use the dense scan directly.
+///
+/// # Returns
+///
+/// A vector of same length as `values1`, where entry `i` gives the index into `values2` matched
+/// to `values1[i]`.
+fn greedy_match(
+    points1: &[Vec<f64>],
+    points2: &[Vec<f64>],
+    metric: &dyn DistanceMetric,
+) -> Vec<usize> {
+    let sorting_order = order_by_distance_from_median(points1, metric);
+
+    use std::collections::HashSet;
+
+    let mut matched = vec![0usize; sorting_order.len()];
+    let mut used_indices = HashSet::new();
+
+    if points2.len() >= KDTREE_MIN_SIZE && metric.supports_kdtree_pruning() {
+        let tree = KdTree::build(points2);
+        for &i in sorting_order.iter() {
+            let min_index = tree
+                .nearest_unused(&points1[i], &used_indices, metric)
+                .expect("values2 must have an unused entry for every values1 entry");
+            used_indices.insert(min_index);
+            matched[i] = min_index;
+        }
+        return matched;
+    }
+
+    for &i in sorting_order.iter() {
+        let mut min_dist = f64::MAX;
+        let mut min_index = 0;
+
+        for (j, point2) in points2.iter().enumerate() {
+            if used_indices.contains(&j) {
+                continue;
+            }
+            let dist = metric.distance(&points1[i], point2);
+
+            if dist < min_dist {
+                min_dist = dist;
+                min_index = j;
+            }
+        }
+
+        used_indices.insert(min_index);
+        matched[i] = min_index;
+    }
+
+    matched
+}
+
+/// Match entries of `values1` to entries of `values2` with the globally minimal total `metric`
+/// cost, via the [`hungarian_assignment`] over the full pairwise cost matrix.
+///
+/// # Returns
+///
+/// A vector of same length as `points1`, where entry `i` gives the index into `points2` matched
+/// to `points1[i]`.
+fn optimal_match_nd(
+    points1: &[Vec<f64>],
+    points2: &[Vec<f64>],
+    metric: &dyn DistanceMetric,
+) -> Vec<usize> {
+    let n = points1.len();
+    let mut cost = DMatrix::zeros(n, n);
+    for i in 0..n {
+        for j in 0..n {
+            cost[(i, j)] = metric.distance(&points1[i], &points2[j]);
+        }
+    }
+
+    hungarian_assignment(&cost)
+}
+
+/// Match entries of `values1` to entries of `values2` with the globally minimal total absolute
+/// distance, by sorting both vectors and pairing equal ranks. This is provably optimal for 1-D
+/// cost |v1 - v2|, and runs in O(n log n).
+///
+/// # Returns
+///
+/// A vector of same length as `values1`, where entry `i` gives the index into `values2` matched
+/// to `values1[i]`.
+fn optimal_match_1d(values1_ref_var: &[f64], values2_ref_var: &[f64]) -> Vec<usize> {
+    let order1 = get_ordering_index(values1_ref_var, false, false);
+    let order2 = get_ordering_index(values2_ref_var, false, false);
+
+    let mut matched = vec![0usize; values1_ref_var.len()];
+    for (rank, &i) in order1.iter().enumerate() {
+        matched[i] = order2[rank];
+    }
+
+    matched
+}
+
+/// Solves the linear assignment problem: given a square cost matrix `cost`, find the permutation
+/// of columns minimising the total cost of matching each row to one column. This is the
+/// Hungarian (Kuhn-Munkres) algorithm, and runs in O(n^3).
+///
+/// This is the general-purpose counterpart to [`optimal_match_1d`], and is intended for cost
+/// matrices built from multi-dimensional distances (see [`crate::distance`]) rather than the
+/// single-variable case.
+///
+/// # Arguments
+///
+/// * `cost` - A square matrix where `cost[(i, j)]` is the cost of matching row `i` to column `j`.
+///
+/// # Panics
+///
+/// This function will panic if `cost` is not square.
+///
+/// # Returns
+///
+/// A vector of same length as `cost` has rows, where entry `i` gives the column matched to row
+/// `i`.
+pub fn hungarian_assignment(cost: &DMatrix<f64>) -> Vec<usize> {
+    let n = cost.nrows();
+    assert_eq!(
+        cost.ncols(),
+        n,
+        "hungarian_assignment requires a square cost matrix"
+    );
+
+    const INF: f64 = f64::MAX / 2.0;
+
+    // 1-indexed internally, following the standard formulation of the algorithm.
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; n + 1];
+    let mut p = vec![0usize; n + 1]; // p[j] = row currently matched to column j.
+    let mut way = vec![0usize; n + 1];
+
+    for i in 1..=n {
+        p[0] = i;
+        let mut j0 = 0usize;
+        let mut minv = vec![INF; n + 1];
+        let mut used = vec![false; n + 1];
+
+        loop {
+            used[j0] = true;
+            let i0 = p[j0];
+            let mut delta = INF;
+            let mut j1 = 0usize;
+
+            for j in 1..=n {
+                if !used[j] {
+                    let cur = cost[(i0 - 1, j - 1)] - u[i0] - v[j];
+                    if cur < minv[j] {
+                        minv[j] = cur;
+                        way[j] = j0;
+                    }
+                    if minv[j] < delta {
+                        delta = minv[j];
+                        j1 = j;
+                    }
+                }
+            }
+
+            for j in 0..=n {
+                if used[j] {
+                    u[p[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+
+            j0 = j1;
+            if p[j0] == 0 {
+                break;
+            }
+        }
+
+        loop {
+            let j1 = way[j0];
+            p[j0] = p[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut result = vec![0usize; n];
+    for j in 1..=n {
+        if p[j] != 0 {
+            result[p[j] - 1] = j - 1;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::{Cosine, Euclidean, Mahalanobis};
+
+    #[test]
+    fn test_calculate_dists_greedy_absolute() {
+        // Traversal order is ascending distance from the geometric median of values1 (here 3.0),
+        // i.e. indices [1, 2, 0, 3] (values 2.0, 4.0, 1.0, 5.0), each matched to the
+        // not-yet-used values2 entry nearest in Euclidean distance.
+        let values1 = DMatrix::from_row_slice(1, 4, &[1.0, 2.0, 4.0, 5.0]);
+        let values2 = DMatrix::from_row_slice(1, 4, &[7.0, 9.0, 3.0, 2.0]);
+        let result = calculate_dists(&values1, &values2, true, MatchingMode::Greedy, &Euclidean);
+        assert_eq!(result, vec![6.0, 0.0, -1.0, 4.0]);
+    }
+
+    #[test]
+    fn test_calculate_dists_greedy_relative() {
+        let values1 = DMatrix::from_row_slice(1, 4, &[1.0, 2.0, 4.0, 5.0]);
+        let values2 = DMatrix::from_row_slice(1, 4, &[7.0, 9.0, 3.0, 2.0]);
+        let result = calculate_dists(&values1, &values2, false, MatchingMode::Greedy, &Euclidean);
+        assert_eq!(result, vec![6.0, 0.0, -0.25, 0.8]);
+    }
+
+    #[test]
+    fn test_calculate_dists_optimal_is_at_least_as_good_as_greedy() {
+        let values1 = DMatrix::from_row_slice(1, 4, &[1.0, 2.0, 4.0, 5.0]);
+        let values2 = DMatrix::from_row_slice(1, 4, &[7.0, 9.0, 3.0, 2.0]);
+
+        let greedy = calculate_dists(&values1, &values2, true, MatchingMode::Greedy, &Euclidean);
+        let optimal = calculate_dists(&values1, &values2, true, MatchingMode::Optimal, &Euclidean);
+
+        let total_greedy: f64 = greedy.iter().map(|d| d.abs()).sum();
+        let total_optimal: f64 = optimal.iter().map(|d| d.abs()).sum();
+        assert!(total_optimal <= total_greedy);
+    }
+
+    #[test]
+    fn test_calculate_dists_uses_all_rows_with_euclidean_metric() {
+        // Points of values1 are (1, 0) and (2, 10); points of values2 are (1, 10) and (2, 0).
+        // Matching on the first row only would pair each entry with the equal first-row value,
+        // but the second row makes (1, 0) nearest to (2, 0) and (2, 10) nearest to (1, 10).
+        let values1 = DMatrix::from_row_slice(2, 2, &[1.0, 2.0, 0.0, 10.0]);
+        let values2 = DMatrix::from_row_slice(2, 2, &[1.0, 2.0, 10.0, 0.0]);
+        let result = calculate_dists(&values1, &values2, true, MatchingMode::Greedy, &Euclidean);
+        // Differences are in the first row only: 2 - 1 = 1 and 1 - 2 = -1.
+        assert_eq!(result, vec![1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_calculate_dists_accepts_cosine_metric() {
+        // Points 0 are `(-3, -3)` and `(4, 5)`; points 1 are `(-2, 5)` and `(1, 1)`. Euclidean
+        // distance prefers pairing point 0 with point 1's second entry (total distance ~11.66 vs
+        // ~13.06 for the direct pairing), but Cosine distance prefers the direct pairing instead
+        // (total ~1.40 vs ~2.51), since `(4, 5)` points almost the same direction as `(1, 1)`
+        // while `(-3, -3)` points exactly opposite to `(1, 1)`. So if `Cosine` were ignored in
+        // favour of Euclidean, this would pick the other pairing.
+        let values1 = DMatrix::from_row_slice(2, 2, &[-3.0, 4.0, -3.0, 5.0]);
+        let values2 = DMatrix::from_row_slice(2, 2, &[-2.0, 1.0, 5.0, 1.0]);
+        let result = calculate_dists(&values1, &values2, true, MatchingMode::Optimal, &Cosine);
+        assert_eq!(result, vec![1.0, -3.0]);
+    }
+
+    #[test]
+    fn test_calculate_dists_accepts_mahalanobis_metric() {
+        // Points 0 are `(0, 0)` and `(0, 5)`; points 1 are `(-1, 2)` and `(6, 20)`. Euclidean
+        // distance prefers the direct pairing (total ~18.39 vs ~24.04 for the swap), but the
+        // combined cloud has far more variance along the second axis than the first, so
+        // Mahalanobis distance down-weights it enough to prefer the swapped pairing instead
+        // (total ~2.76 vs ~4.06). So if `Mahalanobis` were ignored in favour of Euclidean, this
+        // would pick the other pairing.
+        let values1 = DMatrix::from_row_slice(2, 2, &[0.0, 0.0, 0.0, 5.0]);
+        let values2 = DMatrix::from_row_slice(2, 2, &[-1.0, 6.0, 2.0, 20.0]);
+        let points1: Vec<Vec<f64>> = (0..values1.ncols())
+            .map(|c| values1.column(c).iter().cloned().collect())
+            .collect();
+        let points2: Vec<Vec<f64>> = (0..values2.ncols())
+            .map(|c| values2.column(c).iter().cloned().collect())
+            .collect();
+        let metric = Mahalanobis::from_points(&points1, &points2);
+        let result = calculate_dists(&values1, &values2, true, MatchingMode::Optimal, &metric);
+        assert_eq!(result, vec![6.0, -1.0]);
+    }
+
+    #[test]
+    fn test_greedy_match_large_input_uses_kdtree_and_agrees_with_dense_scan() {
+        // More entries than KDTREE_MIN_SIZE, so greedy_match takes the KdTree path. values2 is
+        // values1 in reverse order, so every entry has an exact match available somewhere in
+        // values2, and nearest-neighbour matching should find it with zero distance.
+        let n = KDTREE_MIN_SIZE + 10;
+        let values1_vec: Vec<f64> = (0..n).map(|i| i as f64).collect();
+        let values2_vec: Vec<f64> = (0..n).rev().map(|i| i as f64).collect();
+        let values1 = DMatrix::from_row_slice(1, n, &values1_vec);
+        let values2 = DMatrix::from_row_slice(1, n, &values2_vec);
+
+        let result = calculate_dists(&values1, &values2, true, MatchingMode::Greedy, &Euclidean);
+
+        for &dist in result.iter() {
+            assert!(dist.abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_greedy_match_large_input_with_cosine_metric_falls_back_to_dense_scan() {
+        // Cosine doesn't support k-d tree pruning, so even above KDTREE_MIN_SIZE this must still
+        // use the dense scan rather than producing approximate matches from the tree.
+        let n = KDTREE_MIN_SIZE + 10;
+        let values1_vec: Vec<f64> = (0..n).map(|i| i as f64 + 1.0).collect();
+        let values2_vec: Vec<f64> = (0..n).rev().map(|i| i as f64 + 1.0).collect();
+        let values1 = DMatrix::from_row_slice(1, n, &values1_vec);
+        let values2 = DMatrix::from_row_slice(1, n, &values2_vec);
+
+        let result = calculate_dists(&values1, &values2, true, MatchingMode::Greedy, &Cosine);
+        assert_eq!(result.len(), n);
+    }
+
+    #[test]
+    fn test_optimal_match_1d_is_rank_preserving() {
+        let values1 = [3.0, 1.0, 2.0];
+        let values2 = [30.0, 10.0, 20.0];
+        let matched = optimal_match_1d(&values1, &values2);
+        // Smallest of values1 (index 1) pairs with smallest of values2 (index 1), and so on.
+        assert_eq!(matched, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_hungarian_assignment_matches_brute_force_on_small_matrix() {
+        let cost = DMatrix::from_row_slice(3, 3, &[4.0, 1.0, 3.0, 2.0, 0.0, 5.0, 3.0, 2.0, 2.0]);
+        let assignment = hungarian_assignment(&cost);
+
+        let total: f64 = assignment
+            .iter()
+            .enumerate()
+            .map(|(i, &j)| cost[(i, j)])
+            .sum();
+
+        // Brute-force over all permutations of 3 columns, since n is tiny.
+        let perms = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        let best = perms
+            .iter()
+            .map(|perm| {
+                perm.iter()
+                    .enumerate()
+                    .map(|(i, &j)| cost[(i, j)])
+                    .sum::<f64>()
+            })
+            .fold(f64::MAX, f64::min);
+
+        assert!((total - best).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_hungarian_assignment_is_one_to_one() {
+        let cost = DMatrix::from_row_slice(3, 3, &[4.0, 1.0, 3.0, 2.0, 0.0, 5.0, 3.0, 2.0, 2.0]);
+        let assignment = hungarian_assignment(&cost);
+
+        let mut seen: Vec<usize> = assignment.clone();
+        seen.sort_unstable();
+        assert_eq!(seen, vec![0, 1, 2]);
+    }
+}
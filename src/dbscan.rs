@@ -0,0 +1,166 @@
+//! DBSCAN clustering, used as an alternative to a pre-supplied `groups` vector when aggregating
+//! mutation distances (see [`crate::aggregate_to_groups`]).
+
+use crate::distance::DistanceMetric;
+
+/// Runs DBSCAN over `points`, returning a 1-based group label per point suitable for
+/// [`crate::aggregate_to_groups`].
+///
+/// Points within `eps` of at least `min_points` other points (including themselves) are grown
+/// into density-connected clusters, numbered `1, 2, ...` in the order they are discovered. Points
+/// that are not density-reachable from any cluster ("noise") are each assigned their own
+/// singleton group, numbered after the last real cluster, so that every point always ends up
+/// with a valid group.
+///
+/// # Arguments
+///
+/// * `points` - The point cloud to cluster, one entry per point.
+/// * `eps` - The neighbourhood radius, measured with `metric`.
+/// * `min_points` - The minimum number of neighbours (including the point itself) required to
+/// start or extend a cluster.
+/// * `metric` - The [`DistanceMetric`] used to measure neighbourhood radius.
+///
+/// # Panics
+///
+/// This function will panic if `points` is empty.
+///
+/// # Returns
+///
+/// A vector of same length as `points`, with 1-based group numbers.
+pub fn dbscan(
+    points: &[Vec<f64>],
+    eps: f64,
+    min_points: usize,
+    metric: &dyn DistanceMetric,
+) -> Vec<usize> {
+    assert!(!points.is_empty(), "points must not be empty");
+
+    let n = points.len();
+    let mut labels = vec![0usize; n];
+    let mut visited = vec![false; n];
+    let mut cluster_id = 0usize;
+
+    let region_query = |i: usize| -> Vec<usize> {
+        points
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| metric.distance(&points[i], p) <= eps)
+            .map(|(j, _)| j)
+            .collect()
+    };
+
+    for i in 0..n {
+        if visited[i] {
+            continue;
+        }
+        visited[i] = true;
+
+        let neighbours = region_query(i);
+        if neighbours.len() < min_points {
+            // Not a core point, but may still be pulled in as a border point of some other
+            // cluster later on; that's resolved by the final `labels[i] == 0` sweep below.
+            continue;
+        }
+
+        cluster_id += 1;
+        labels[i] = cluster_id;
+
+        let mut seeds = neighbours;
+        let mut idx = 0;
+        while idx < seeds.len() {
+            let j = seeds[idx];
+            idx += 1;
+
+            if !visited[j] {
+                visited[j] = true;
+                let j_neighbours = region_query(j);
+                if j_neighbours.len() >= min_points {
+                    for k in j_neighbours {
+                        if !seeds.contains(&k) {
+                            seeds.push(k);
+                        }
+                    }
+                }
+            }
+
+            if labels[j] == 0 {
+                labels[j] = cluster_id;
+            }
+        }
+    }
+
+    // Points never pulled into any cluster, as a core point or a border point, are noise; each
+    // becomes its own singleton group, numbered after the real clusters.
+    let mut next_id = cluster_id;
+    for label in labels.iter_mut() {
+        if *label == 0 {
+            next_id += 1;
+            *label = next_id;
+        }
+    }
+
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::Euclidean;
+
+    #[test]
+    fn test_dbscan_finds_two_dense_clusters() {
+        let points = vec![
+            vec![0.0, 0.0],
+            vec![0.1, 0.0],
+            vec![0.0, 0.1],
+            vec![10.0, 10.0],
+            vec![10.1, 10.0],
+            vec![10.0, 10.1],
+        ];
+        let labels = dbscan(&points, 0.5, 2, &Euclidean);
+
+        assert_eq!(labels[0], labels[1]);
+        assert_eq!(labels[1], labels[2]);
+        assert_eq!(labels[3], labels[4]);
+        assert_eq!(labels[4], labels[5]);
+        assert_ne!(labels[0], labels[3]);
+    }
+
+    #[test]
+    fn test_dbscan_isolates_noise_into_singleton_groups() {
+        let points = vec![
+            vec![0.0, 0.0],
+            vec![0.1, 0.0],
+            vec![0.0, 0.1],
+            vec![50.0, 50.0],
+        ];
+        let labels = dbscan(&points, 0.5, 2, &Euclidean);
+
+        // The last point is far from everything else, so it forms its own singleton group.
+        assert_ne!(labels[3], labels[0]);
+        let noise_group_count = labels.iter().filter(|&&g| g == labels[3]).count();
+        assert_eq!(noise_group_count, 1);
+    }
+
+    #[test]
+    fn test_dbscan_labels_are_one_based() {
+        let points = vec![vec![0.0], vec![0.05], vec![0.1]];
+        let labels = dbscan(&points, 0.5, 2, &Euclidean);
+        assert!(labels.iter().all(|&g| g >= 1));
+    }
+
+    #[test]
+    fn test_dbscan_keeps_border_point_visited_as_noise_first() {
+        // Point 0 is visited first and, on its own, isn't dense enough to start a cluster. Point
+        // 1 is later found to be a core point whose neighbourhood includes point 0, pulling it in
+        // as a legitimate border point. Point 0 must stay in that cluster rather than being
+        // overwritten by the final noise sweep.
+        let points = vec![vec![0.0], vec![1.0], vec![1.8], vec![2.6], vec![10.0]];
+        let labels = dbscan(&points, 1.5, 3, &Euclidean);
+
+        assert_eq!(labels[0], labels[1]);
+        assert_eq!(labels[1], labels[2]);
+        assert_eq!(labels[2], labels[3]);
+        assert_ne!(labels[4], labels[0]);
+    }
+}
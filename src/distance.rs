@@ -0,0 +1,175 @@
+//! Pluggable distance metrics used to compare points in the multi-dimensional space spanned by
+//! `varname` and any `varextra` columns.
+
+use nalgebra::DMatrix;
+
+/// A distance metric between two points, each represented as a slice of coordinates (one per
+/// variable).
+pub trait DistanceMetric {
+    /// Computes the distance between points `a` and `b`.
+    ///
+    /// # Panics
+    ///
+    /// Implementations may panic if `a` and `b` have different lengths.
+    fn distance(&self, a: &[f64], b: &[f64]) -> f64;
+
+    /// Whether [`crate::kdtree::KdTree`]'s axis-aligned pruning is exact under this metric, i.e.
+    /// the metric is lower-bounded by the per-axis coordinate difference. True for `Euclidean`;
+    /// `false` by default, and for `Cosine` and `Mahalanobis`, since neither is lower-bounded by
+    /// the raw per-axis difference (`Mahalanobis` rescales and mixes axes). Callers should fall back to a dense scan when this is
+    /// `false`.
+    fn supports_kdtree_pruning(&self) -> bool {
+        false
+    }
+}
+
+/// Ordinary straight-line (L2) distance.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Euclidean;
+
+impl DistanceMetric for Euclidean {
+    fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        assert_eq!(a.len(), b.len(), "points must have the same dimension");
+        a.iter()
+            .zip(b.iter())
+            .map(|(x, y)| (x - y).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn supports_kdtree_pruning(&self) -> bool {
+        true
+    }
+}
+
+/// Cosine distance, `1 - (a.b) / (||a|| ||b||)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cosine;
+
+impl DistanceMetric for Cosine {
+    fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        assert_eq!(a.len(), b.len(), "points must have the same dimension");
+        let dot: f64 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+        let norm_a = a.iter().map(|x| x.powi(2)).sum::<f64>().sqrt();
+        let norm_b = b.iter().map(|y| y.powi(2)).sum::<f64>().sqrt();
+        if norm_a == 0.0 || norm_b == 0.0 {
+            return 1.0;
+        }
+        1.0 - dot / (norm_a * norm_b)
+    }
+}
+
+/// Mahalanobis distance, `sqrt((a - b)^T M (a - b))`, where `M` is the inverse covariance matrix
+/// of some reference point cloud. Weighting by the inverse covariance prevents correlated
+/// `varextra` dimensions from dominating the distance.
+#[derive(Debug, Clone)]
+pub struct Mahalanobis {
+    inv_cov: DMatrix<f64>,
+}
+
+impl Mahalanobis {
+    /// Builds a [`Mahalanobis`] metric from the inverse covariance matrix of the combined point
+    /// cloud formed by `points1` and `points2`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `points1` and `points2` are both empty.
+    pub fn from_points(points1: &[Vec<f64>], points2: &[Vec<f64>]) -> Self {
+        let combined: Vec<&Vec<f64>> = points1.iter().chain(points2.iter()).collect();
+        assert!(!combined.is_empty(), "point cloud must not be empty");
+        let dim = combined[0].len();
+        let n = combined.len() as f64;
+
+        let mut mean = vec![0.0; dim];
+        for point in &combined {
+            for (m, x) in mean.iter_mut().zip(point.iter()) {
+                *m += x / n;
+            }
+        }
+
+        let mut cov = DMatrix::zeros(dim, dim);
+        for point in &combined {
+            let diff = DMatrix::from_row_slice(
+                dim,
+                1,
+                &point
+                    .iter()
+                    .zip(mean.iter())
+                    .map(|(x, m)| x - m)
+                    .collect::<Vec<f64>>(),
+            );
+            cov += &diff * diff.transpose();
+        }
+        if n > 1.0 {
+            cov /= n - 1.0;
+        }
+
+        let inv_cov = cov.clone().try_inverse().unwrap_or_else(|| {
+            // Fall back to the identity (i.e. Euclidean distance) if the covariance matrix is
+            // singular, e.g. because a variable is constant.
+            DMatrix::identity(dim, dim)
+        });
+
+        Mahalanobis { inv_cov }
+    }
+}
+
+impl DistanceMetric for Mahalanobis {
+    fn distance(&self, a: &[f64], b: &[f64]) -> f64 {
+        assert_eq!(a.len(), b.len(), "points must have the same dimension");
+        let diff = DMatrix::from_row_slice(
+            a.len(),
+            1,
+            &a.iter().zip(b).map(|(x, y)| x - y).collect::<Vec<f64>>(),
+        );
+        let result = (&diff.transpose() * &self.inv_cov * &diff)[(0, 0)];
+        result.max(0.0).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_euclidean_distance() {
+        let a = [1.0, 2.0];
+        let b = [4.0, 6.0];
+        assert_eq!(Euclidean.distance(&a, &b), 5.0);
+    }
+
+    #[test]
+    fn test_only_euclidean_supports_kdtree_pruning() {
+        assert!(Euclidean.supports_kdtree_pruning());
+        assert!(!Cosine.supports_kdtree_pruning());
+        let points1 = vec![vec![0.0, 0.0], vec![1.0, 0.0], vec![0.0, 1.0]];
+        let points2 = vec![vec![1.0, 1.0], vec![0.0, 0.0], vec![1.0, 0.0]];
+        assert!(!Mahalanobis::from_points(&points1, &points2).supports_kdtree_pruning());
+    }
+
+    #[test]
+    fn test_cosine_distance_identical_direction() {
+        let a = [1.0, 1.0];
+        let b = [2.0, 2.0];
+        assert!(Cosine.distance(&a, &b).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_cosine_distance_orthogonal() {
+        let a = [1.0, 0.0];
+        let b = [0.0, 1.0];
+        assert!((Cosine.distance(&a, &b) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mahalanobis_reduces_to_euclidean_for_identity_covariance() {
+        // The four points `(+-1, +-1)` plus the origin have zero mean and a sample covariance of
+        // exactly the identity matrix.
+        let points1 = vec![vec![1.0, 1.0], vec![1.0, -1.0], vec![0.0, 0.0]];
+        let points2 = vec![vec![-1.0, 1.0], vec![-1.0, -1.0]];
+        let metric = Mahalanobis::from_points(&points1, &points2);
+        let a = [0.0, 0.0];
+        let b = [3.0, 4.0];
+        assert!((metric.distance(&a, &b) - Euclidean.distance(&a, &b)).abs() < 1e-12);
+    }
+}
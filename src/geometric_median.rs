@@ -0,0 +1,146 @@
+//! A robust, outlier-resistant reference point for a point cloud, used to order the traversal of
+//! [`crate::calculate_dists::calculate_dists`]'s greedy matching instead of anchoring on a single
+//! extreme coordinate.
+
+use crate::distance::DistanceMetric;
+use crate::vector_fns::get_ordering_index;
+
+/// Computes the geometric median of `points` via Weiszfeld's iteration: starting from the
+/// coordinate-wise mean, repeatedly re-estimate the median as the weighted mean of all points,
+/// each weighted by the inverse of its distance to the current estimate, until the estimate
+/// changes by less than `tol` or `max_iter` iterations have been run.
+///
+/// # Arguments
+///
+/// * `points` - The point cloud, one entry per point.
+/// * `metric` - The [`DistanceMetric`] used to measure distances during the iteration. The
+/// geometric median is classically defined with respect to Euclidean distance; other metrics
+/// yield a robust reference point in that metric's geometry rather than the strict geometric
+/// median, but are accepted here for consistency with the rest of the matching pipeline.
+/// * `tol` - The iteration stops once consecutive estimates differ by less than this amount.
+/// * `max_iter` - The maximum number of iterations to run.
+///
+/// # Panics
+///
+/// This function will panic if `points` is empty.
+///
+/// # Returns
+///
+/// The estimated geometric median, as a point of the same dimension as entries of `points`.
+pub fn geometric_median(
+    points: &[Vec<f64>],
+    metric: &dyn DistanceMetric,
+    tol: f64,
+    max_iter: usize,
+) -> Vec<f64> {
+    assert!(!points.is_empty(), "points must not be empty");
+    let dim = points[0].len();
+    let n = points.len() as f64;
+
+    let mut median: Vec<f64> = (0..dim)
+        .map(|d| points.iter().map(|p| p[d]).sum::<f64>() / n)
+        .collect();
+
+    for _ in 0..max_iter {
+        let mut weighted_sum = vec![0.0; dim];
+        let mut weight_total = 0.0;
+
+        for point in points {
+            let dist = metric.distance(point, &median);
+            if dist < f64::EPSILON {
+                // Skip points that coincide exactly with the current estimate, to avoid dividing
+                // by zero.
+                continue;
+            }
+            let weight = 1.0 / dist;
+            for (d, &x) in point.iter().enumerate() {
+                weighted_sum[d] += weight * x;
+            }
+            weight_total += weight;
+        }
+
+        if weight_total == 0.0 {
+            // Every point coincides with the current estimate, so it is already the median.
+            break;
+        }
+
+        let next_median: Vec<f64> = weighted_sum.iter().map(|&s| s / weight_total).collect();
+        let shift = metric.distance(&next_median, &median);
+        median = next_median;
+
+        if shift < tol {
+            break;
+        }
+    }
+
+    median
+}
+
+/// Orders the indices of `points` by ascending distance from their geometric median, giving a
+/// stable, outlier-resistant traversal order that doesn't privilege any single variable.
+///
+/// # Returns
+///
+/// A vector of indices into `points`, ordered by ascending distance from the geometric median.
+pub fn order_by_distance_from_median(
+    points: &[Vec<f64>],
+    metric: &dyn DistanceMetric,
+) -> Vec<usize> {
+    let median = geometric_median(points, metric, 1e-6, 100);
+    let distances: Vec<f64> = points.iter().map(|p| metric.distance(p, &median)).collect();
+    get_ordering_index(&distances, false, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::Euclidean;
+
+    #[test]
+    fn test_geometric_median_of_symmetric_points_is_their_centre() {
+        let points = vec![
+            vec![0.0, 0.0],
+            vec![2.0, 0.0],
+            vec![1.0, 1.0],
+            vec![1.0, -1.0],
+        ];
+        let median = geometric_median(&points, &Euclidean, 1e-9, 200);
+        assert!((median[0] - 1.0).abs() < 1e-6);
+        assert!((median[1] - 0.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_geometric_median_is_robust_to_a_single_outlier() {
+        // An outlier at (1000, 1000) should barely move the median away from the tight cluster
+        // near the origin, unlike the coordinate-wise mean.
+        let mut points = vec![
+            vec![0.0, 0.0],
+            vec![0.1, 0.0],
+            vec![0.0, 0.1],
+            vec![0.1, 0.1],
+        ];
+        points.push(vec![1000.0, 1000.0]);
+        let median = geometric_median(&points, &Euclidean, 1e-9, 200);
+        assert!(median[0] < 1.0);
+        assert!(median[1] < 1.0);
+    }
+
+    #[test]
+    fn test_order_by_distance_from_median_is_ascending() {
+        let points = vec![
+            vec![0.0, 0.0],
+            vec![10.0, 10.0],
+            vec![0.1, 0.1],
+            vec![5.0, 5.0],
+        ];
+        let order = order_by_distance_from_median(&points, &Euclidean);
+        let median = geometric_median(&points, &Euclidean, 1e-9, 200);
+        let distances: Vec<f64> = order
+            .iter()
+            .map(|&i| Euclidean.distance(&points[i], &median))
+            .collect();
+        for window in distances.windows(2) {
+            assert!(window[0] <= window[1]);
+        }
+    }
+}
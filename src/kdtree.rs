@@ -0,0 +1,162 @@
+//! A k-d tree over a static point cloud, used to find nearest not-yet-matched neighbours without
+//! the linear scan that [`crate::calculate_dists::calculate_dists`] would otherwise need for
+//! every point of `values1`.
+
+use crate::distance::DistanceMetric;
+use std::collections::HashSet;
+
+struct KdNode {
+    point_index: usize,
+    axis: usize,
+    left: Option<Box<KdNode>>,
+    right: Option<Box<KdNode>>,
+}
+
+/// A k-d tree built over a fixed set of `points`, supporting repeated nearest-neighbour queries
+/// that skip a caller-supplied set of already-used points.
+///
+/// The tree's splits are taken along raw coordinate axes, so pruning is exact for metrics whose
+/// distance is lower-bounded by the per-axis coordinate difference (e.g. [`crate::distance::Euclidean`]).
+/// For metrics without that property (e.g. [`crate::distance::Cosine`] and
+/// [`crate::distance::Mahalanobis`]), queries still return a candidate nearest neighbour by evaluating
+/// `metric` at every visited node, but the pruning may not be tight, so results can be
+/// approximate; prefer a dense scan for those metrics.
+pub struct KdTree<'a> {
+    points: &'a [Vec<f64>],
+    root: Option<Box<KdNode>>,
+}
+
+impl<'a> KdTree<'a> {
+    /// Builds a k-d tree over `points`, choosing the split axis at each depth by cycling through
+    /// dimensions and splitting on the median.
+    pub fn build(points: &'a [Vec<f64>]) -> Self {
+        let dim = points.first().map_or(0, |p| p.len());
+        let mut indices: Vec<usize> = (0..points.len()).collect();
+        let root = Self::build_recursive(points, &mut indices, 0, dim);
+        KdTree { points, root }
+    }
+
+    fn build_recursive(
+        points: &[Vec<f64>],
+        indices: &mut [usize],
+        depth: usize,
+        dim: usize,
+    ) -> Option<Box<KdNode>> {
+        if indices.is_empty() || dim == 0 {
+            return None;
+        }
+
+        let axis = depth % dim;
+        indices.sort_by(|&a, &b| points[a][axis].partial_cmp(&points[b][axis]).unwrap());
+        let mid = indices.len() / 2;
+        let point_index = indices[mid];
+
+        let (left_indices, rest) = indices.split_at_mut(mid);
+        let right_indices = &mut rest[1..];
+
+        let left = Self::build_recursive(points, left_indices, depth + 1, dim);
+        let right = Self::build_recursive(points, right_indices, depth + 1, dim);
+
+        Some(Box::new(KdNode {
+            point_index,
+            axis,
+            left,
+            right,
+        }))
+    }
+
+    /// Finds the index into `points` of the nearest point to `target` not present in `used`,
+    /// measured with `metric`. Among equally near points, the one with the lowest index is
+    /// returned, as in a dense scan. Returns `None` if every point is used.
+    pub fn nearest_unused(
+        &self,
+        target: &[f64],
+        used: &HashSet<usize>,
+        metric: &dyn DistanceMetric,
+    ) -> Option<usize> {
+        let mut best: Option<(usize, f64)> = None;
+        Self::search(&self.root, self.points, target, used, metric, &mut best);
+        best.map(|(index, _)| index)
+    }
+
+    fn search(
+        node: &Option<Box<KdNode>>,
+        points: &[Vec<f64>],
+        target: &[f64],
+        used: &HashSet<usize>,
+        metric: &dyn DistanceMetric,
+        best: &mut Option<(usize, f64)>,
+    ) {
+        let Some(node) = node else { return };
+        let point = &points[node.point_index];
+
+        if !used.contains(&node.point_index) {
+            let dist = metric.distance(target, point);
+            if best.map_or(true, |(best_index, best_dist)| {
+                dist < best_dist || (dist == best_dist && node.point_index < best_index)
+            }) {
+                *best = Some((node.point_index, dist));
+            }
+        }
+
+        let axis_diff = target[node.axis] - point[node.axis];
+        let (near, far) = if axis_diff <= 0.0 {
+            (&node.left, &node.right)
+        } else {
+            (&node.right, &node.left)
+        };
+
+        Self::search(near, points, target, used, metric, best);
+
+        // The far branch can only hold a point at least as close as the current best if the
+        // splitting plane itself is within that distance.
+        if best.map_or(true, |(_, best_dist)| axis_diff.abs() <= best_dist) {
+            Self::search(far, points, target, used, metric, best);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::Euclidean;
+
+    #[test]
+    fn test_nearest_unused_matches_dense_scan() {
+        let points = vec![
+            vec![0.0, 0.0],
+            vec![5.0, 5.0],
+            vec![1.0, 1.0],
+            vec![9.0, 0.0],
+            vec![2.0, 3.0],
+        ];
+        let tree = KdTree::build(&points);
+        let target = vec![0.5, 0.5];
+        let used = HashSet::new();
+
+        let found = tree.nearest_unused(&target, &used, &Euclidean).unwrap();
+        assert_eq!(found, 0);
+    }
+
+    #[test]
+    fn test_nearest_unused_skips_used_points() {
+        let points = vec![vec![0.0, 0.0], vec![1.0, 1.0], vec![2.0, 2.0]];
+        let tree = KdTree::build(&points);
+        let target = vec![0.0, 0.0];
+
+        let mut used = HashSet::new();
+        used.insert(0);
+
+        let found = tree.nearest_unused(&target, &used, &Euclidean).unwrap();
+        assert_eq!(found, 1);
+    }
+
+    #[test]
+    fn test_nearest_unused_returns_none_when_all_used() {
+        let points = vec![vec![0.0, 0.0], vec![1.0, 1.0]];
+        let tree = KdTree::build(&points);
+        let used: HashSet<usize> = [0, 1].into_iter().collect();
+
+        assert_eq!(tree.nearest_unused(&[0.0, 0.0], &used, &Euclidean), None);
+    }
+}
@@ -7,9 +7,26 @@ use std::fs::File;
 use std::io::BufReader;
 
 pub mod calculate_dists;
+pub mod dbscan;
+pub mod distance;
+pub mod geometric_median;
+pub mod kdtree;
 pub mod mlr;
+pub mod ranking;
 pub mod read_write_file;
 pub mod transform;
+pub mod vector_fns;
+
+/// Selects how `values1` entries are grouped before mutation distances are aggregated in
+/// [`aggregate_to_groups`].
+pub enum GroupingMode {
+    /// Use the 1-based `groups` vector read from the input file's `index` column.
+    Supplied,
+    /// Derive groups automatically by clustering `values1` with DBSCAN, using `eps` as the
+    /// neighbourhood radius and `min_points` as the minimum cluster size. Points not
+    /// density-reachable from any cluster each form their own singleton group.
+    Dbscan { eps: f64, min_points: usize },
+}
 
 /// This is the main function, which reads data from two JSON files, calculates absolute and
 /// relative differences between the two sets of data, and writes the results to an output file.
@@ -22,11 +39,24 @@ pub mod transform;
 /// * `varname` - Name of variable in both `fname1` and `fname2` to be mutated.
 /// * `varextra` - Extra variables to be considered in the mutation.
 /// * `nentries` - The number of entries to be read from the JSON files.
+/// * `mode` - The [`calculate_dists::MatchingMode`] used to pair entries of `values1` with entries
+/// of `values2`.
+/// * `metric` - The [`distance::DistanceMetric`] used to compare points across all rows, both
+/// when matching `values1` to `values2` and, if `grouping` is [`GroupingMode::Dbscan`], when
+/// clustering `values1`.
+/// * `grouping` - The [`GroupingMode`] used to assign `values1` entries to groups before
+/// aggregating mutation distances.
 ///
 /// # Returns
 ///
-/// A vector of length equal to number of distinct groups in the input data 'index' column, with
-/// each value quantifying the mean distance to the nearest points in the target distribution.
+/// A tuple of:
+/// 1. A `DMatrix` of length equal to number of distinct groups in the input data 'index' column,
+/// with each value quantifying the mean distance to the nearest points in the target
+/// distribution.
+/// 2. The `nDCG@k` ranking-quality score (see [`ranking::ndcg_at_k`]) of the underlying
+/// per-entry mutation distances, with `k` set to the number of distinct groups. This quantifies
+/// how well the largest mutations coincide with the largest proximity improvements, and so gives
+/// a single 0-1 score on which different parameter settings or variable choices can be compared.
 ///
 /// # Process
 ///
@@ -47,7 +77,10 @@ pub fn uamutate(
     reader2: BufReader<File>,
     varnames: &Vec<String>,
     nentries: usize,
-) -> DMatrix<f64> {
+    mode: calculate_dists::MatchingMode,
+    metric: &dyn distance::DistanceMetric,
+    grouping: GroupingMode,
+) -> (DMatrix<f64>, f64) {
     // Read contents of files:
     let (mut values1, groups1) = read_write_file::readfile(reader1, varnames, nentries);
     let (mut values2, _groups2) = read_write_file::readfile(reader2, varnames, nentries);
@@ -63,19 +96,35 @@ pub fn uamutate(
     // Then calculate successive differences between the two sets of values. These are the
     // distances by which `values1` need to be moved in the first dimension only to match the
     // closest equivalent values of `values2`.
-    let dists = calculate_dists::calculate_dists(&values1, &values2);
-    aggregate_to_groups(&values1, &dists, &groups1)
+    let dists = calculate_dists::calculate_dists(&values1, &values2, true, mode, metric);
+
+    let groups1 = match grouping {
+        GroupingMode::Supplied => groups1,
+        GroupingMode::Dbscan { eps, min_points } => {
+            let points1: Vec<Vec<f64>> = (0..values1.ncols())
+                .map(|c| values1.column(c).iter().cloned().collect())
+                .collect();
+            dbscan::dbscan(&points1, eps, min_points, metric)
+        }
+    };
+
+    let num_groups = groups1.iter().collect::<std::collections::HashSet<_>>().len();
+    let ndcg = ranking::ndcg_at_k(&dists, num_groups);
+
+    (aggregate_to_groups(&values1, &dists, &groups1), ndcg)
 }
 
-/// Loop over all columns of the `dists` `DMatrix` object, and aggregate groups for each column.
+/// Aggregate `values1` and the mutation distances `dists_abs` (as returned by
+/// [`calculate_dists::calculate_dists`]) within the groups defined by `groups`.
 ///
 /// # Arguments
 ///
 /// * `values1` - The original values used as references for the distances; aggregated versions of
 /// these are also returned.
-/// * `dists` - A matrix of distances between entries in `values1` and closest values in `values2`.
-/// * `groups` - A vector of same length as `dists`, with 1-based indices of group numbers. There
-/// will generally be far fewer unique groups as there are entries in `dists`.
+/// * `dists_abs` - Absolute distances between entries in `values1` and closest values in
+/// `values2`, one per entry of `values1`.
+/// * `groups` - A vector of same length as `dists_abs`, with 1-based indices of group numbers.
+/// There will generally be far fewer unique groups as there are entries in `dists_abs`.
 ///
 /// # Returns
 ///
@@ -88,10 +137,10 @@ pub fn uamutate(
 /// 4. The relative difference between mutate and original values
 fn aggregate_to_groups(
     values1: &DMatrix<f64>,
-    dists: &DMatrix<f64>,
+    dists_abs: &[f64],
     groups: &[usize],
 ) -> DMatrix<f64> {
-    let mut result = DMatrix::zeros(groups.len(), dists.ncols() + 2);
+    let mut result = DMatrix::zeros(groups.len(), 4);
 
     // Aggregate original values first:
     let values1_first_col: Vec<f64> = values1.column(0).iter().cloned().collect();
@@ -100,8 +149,7 @@ fn aggregate_to_groups(
         result[(i, 0)] = value;
     }
 
-    // Then generate absolute transformed value from original value plus  absolute distance:
-    let dists_abs: Vec<f64> = dists.column(0).iter().cloned().collect();
+    // Then generate absolute transformed value from original value plus absolute distance:
     let values1_transformed: Vec<f64> = values1_first_col
         .iter()
         .zip(dists_abs.iter())
@@ -111,13 +159,20 @@ fn aggregate_to_groups(
         result[(i, 1)] = value;
     }
 
-    // Then both absolute and relative distances:
-    for col in 0..dists.ncols() {
-        let dists_col: Vec<f64> = dists.column(col).iter().cloned().collect();
-        let mean_dist = aggregate_to_groups_single_col(&dists_col, groups);
-        for (i, &value) in mean_dist.iter().enumerate() {
-            result[(i, col + 2)] = value;
-        }
+    // Then the absolute distances, and the relative distances derived from them:
+    let mean_dist = aggregate_to_groups_single_col(dists_abs, groups);
+    for (i, &value) in mean_dist.iter().enumerate() {
+        result[(i, 2)] = value;
+    }
+
+    let dists_rel: Vec<f64> = dists_abs
+        .iter()
+        .zip(values1_first_col.iter())
+        .map(|(&d, &v1)| d / v1)
+        .collect();
+    let mean_dist = aggregate_to_groups_single_col(&dists_rel, groups);
+    for (i, &value) in mean_dist.iter().enumerate() {
+        result[(i, 3)] = value;
     }
 
     result
@@ -185,8 +240,17 @@ mod tests {
         let reader1 = BufReader::new(file1);
         let file2 = File::open(filename2).unwrap();
         let reader2 = BufReader::new(file2);
-        let sums = uamutate(reader1, reader2, &varsall, nentries);
+        let (sums, ndcg) = uamutate(
+            reader1,
+            reader2,
+            &varsall,
+            nentries,
+            calculate_dists::MatchingMode::Greedy,
+            &distance::Euclidean,
+            GroupingMode::Supplied,
+        );
 
         assert!(!sums.is_empty());
+        assert!((0.0..=1.0).contains(&ndcg));
     }
 }
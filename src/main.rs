@@ -15,5 +15,16 @@ const OUTFILENAME: &str = "output.txt";
 ///
 /// This exists only to locally call and run the library.
 fn main() {
-    uamutations::uamutate(FNAME1, FNAME2, VARNAME, NENTRIES, OUTFILENAME);
+    let reader1 = std::io::BufReader::new(std::fs::File::open(FNAME1).unwrap());
+    let reader2 = std::io::BufReader::new(std::fs::File::open(FNAME2).unwrap());
+    let varnames = vec![VARNAME.to_string()];
+    uamutations::uamutate(
+        reader1,
+        reader2,
+        &varnames,
+        NENTRIES,
+        uamutations::calculate_dists::MatchingMode::Greedy,
+        &uamutations::distance::Euclidean,
+        uamutations::GroupingMode::Supplied,
+    );
 }
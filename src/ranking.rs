@@ -0,0 +1,98 @@
+//! Ranking-quality diagnostics for the mutation distances produced by
+//! [`crate::calculate_dists::calculate_dists`].
+
+use crate::vector_fns::get_ordering_index;
+
+/// Computes the normalised discounted cumulative gain (nDCG) at cutoff `k` over a set of matched
+/// pairs.
+///
+/// Entries are ranked by their signed mutation distance (`dists`, descending), and each is
+/// assigned a relevance equal to the improvement in proximity to its matched target, i.e. the
+/// absolute distance closed by the mutation, `|dists_i|`. `DCG@k` sums `rel_i / log2(i + 1)` over
+/// the top `k` ranked entries, and is normalised by the ideal DCG (the same relevances sorted
+/// descending) to give a score in `[0, 1]`: a value of 1 means the `k` largest mutations are
+/// exactly those with the `k` largest proximity improvements; lower values mean signed direction
+/// and improvement magnitude diverge, e.g. because large negative mutations are ranked low by raw
+/// signed value despite closing just as large a gap as large positive ones.
+///
+/// # Arguments
+///
+/// * `dists` - Signed mutation distances, one per matched pair, as returned by
+/// [`crate::calculate_dists::calculate_dists`].
+/// * `k` - The cutoff rank. Only the top `k` ranked entries contribute to `DCG@k`. Values of `k`
+/// greater than `dists.len()` are clamped to `dists.len()`.
+///
+/// # Panics
+///
+/// This function will panic if `dists` is empty.
+///
+/// # Returns
+///
+/// `nDCG@k`, a value in `[0, 1]`. Returns `0.0` if every relevance is zero (i.e. every matched
+/// pair already coincides with its target).
+pub fn ndcg_at_k(dists: &[f64], k: usize) -> f64 {
+    assert!(!dists.is_empty(), "dists must not be empty");
+    let k = k.min(dists.len());
+
+    let dcg_of = |relevances: &[f64]| -> f64 {
+        relevances
+            .iter()
+            .take(k)
+            .enumerate()
+            .map(|(i, rel)| rel / ((i as f64) + 2.0).log2())
+            .sum()
+    };
+
+    let order = get_ordering_index(dists, true, false);
+    let ranked_relevances: Vec<f64> = order.iter().map(|&i| dists[i].abs()).collect();
+    let dcg = dcg_of(&ranked_relevances);
+
+    let mut ideal_relevances: Vec<f64> = dists.iter().map(|d| d.abs()).collect();
+    ideal_relevances.sort_by(|a, b| b.partial_cmp(a).unwrap());
+    let idcg = dcg_of(&ideal_relevances);
+
+    if idcg == 0.0 {
+        0.0
+    } else {
+        dcg / idcg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ndcg_is_one_when_signed_order_matches_magnitude_order() {
+        let dists = vec![5.0, 3.0, 1.0];
+        assert_eq!(ndcg_at_k(&dists, 3), 1.0);
+    }
+
+    #[test]
+    fn test_ndcg_is_below_one_when_large_negative_mutation_is_ranked_low() {
+        // -5.0 has the largest magnitude (best improvement) but sorts last by signed value.
+        let dists = vec![1.0, 2.0, -5.0];
+        let score = ndcg_at_k(&dists, 3);
+        assert!(score < 1.0);
+        assert!(score > 0.0);
+    }
+
+    #[test]
+    fn test_ndcg_cutoff_k_smaller_than_length() {
+        let dists = vec![5.0, 3.0, 1.0, -10.0];
+        let score = ndcg_at_k(&dists, 2);
+        assert!(score > 0.0 && score <= 1.0);
+    }
+
+    #[test]
+    fn test_ndcg_zero_when_all_relevances_zero() {
+        let dists = vec![0.0, 0.0, 0.0];
+        assert_eq!(ndcg_at_k(&dists, 3), 0.0);
+    }
+
+    #[test]
+    fn test_ndcg_clamps_k_larger_than_length() {
+        let dists = vec![2.0, 1.0];
+        assert_eq!(ndcg_at_k(&dists, 100), ndcg_at_k(&dists, 2));
+    }
+}